@@ -0,0 +1,257 @@
+//! load the global config and layer a per-repository `.rwth-lkp-submit.toml` over it,
+//! so a single lab checkout can pin its own lab/task and base commit.
+//!
+//! Every `Config` section has a `Partial*` counterpart below with the same fields
+//! wrapped in `Option`, so a repo-local file only has to mention what it overrides;
+//! `merge_over` then takes each present field from the repo config and falls back to
+//! the corresponding field of the global config otherwise.
+use std::env::current_dir;
+use std::fmt::Display;
+use std::fs::{create_dir_all, read_to_string, File};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::exit;
+
+use anyhow::Context;
+use directories::ProjectDirs;
+use git2::Repository;
+use serde::{Deserialize, Serialize};
+
+use crate::lint::{LintConfig, RuleName};
+use crate::mail::{MailBackend, MailConfig, SmtpConfig};
+use crate::ACCENT;
+
+const REPO_CONFIG_FILE_NAME: &str = ".rwth-lkp-submit.toml";
+
+#[derive(Debug, Deserialize, Clone, Serialize)]
+#[serde(default)]
+pub struct LabTask {
+    pub lab: u32,
+    pub task: u32,
+}
+
+impl Display for LabTask {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "lab{}: task{}:", self.lab, self.task)
+    }
+}
+
+impl Default for LabTask {
+    fn default() -> Self {
+        Self { lab: 3, task: 2 }
+    }
+}
+
+#[derive(Debug, Deserialize, Clone, Serialize)]
+#[serde(default)]
+pub struct GitConfig {
+    pub root_commit: String,
+}
+
+impl Default for GitConfig {
+    fn default() -> Self {
+        Self {
+            root_commit: "v6.5.7".to_owned(),
+        }
+    }
+}
+
+#[derive(Debug, Default, Deserialize, Clone, Serialize)]
+#[serde(default)]
+pub struct Config {
+    pub test: LabTask,
+    pub mail: MailConfig,
+    pub smtp: SmtpConfig,
+    pub git: GitConfig,
+    pub lint: LintConfig,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct PartialLabTask {
+    lab: Option<u32>,
+    task: Option<u32>,
+}
+
+impl PartialLabTask {
+    fn merge_over(self, base: LabTask) -> LabTask {
+        LabTask {
+            lab: self.lab.unwrap_or(base.lab),
+            task: self.task.unwrap_or(base.task),
+        }
+    }
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct PartialMailConfig {
+    to: Option<String>,
+    suppress_cc: Option<bool>,
+    backend: Option<MailBackend>,
+}
+
+impl PartialMailConfig {
+    fn merge_over(self, base: MailConfig) -> MailConfig {
+        MailConfig {
+            to: self.to.unwrap_or(base.to),
+            suppress_cc: self.suppress_cc.unwrap_or(base.suppress_cc),
+            backend: self.backend.unwrap_or(base.backend),
+        }
+    }
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct PartialSmtpConfig {
+    host: Option<String>,
+    port: Option<u16>,
+    username: Option<String>,
+    password: Option<String>,
+    credential_command: Option<String>,
+    starttls: Option<bool>,
+    from: Option<String>,
+}
+
+impl PartialSmtpConfig {
+    fn merge_over(self, base: SmtpConfig) -> SmtpConfig {
+        SmtpConfig {
+            host: self.host.unwrap_or(base.host),
+            port: self.port.unwrap_or(base.port),
+            username: self.username.unwrap_or(base.username),
+            password: self.password.unwrap_or(base.password),
+            credential_command: self.credential_command.unwrap_or(base.credential_command),
+            starttls: self.starttls.unwrap_or(base.starttls),
+            from: self.from.unwrap_or(base.from),
+        }
+    }
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct PartialGitConfig {
+    root_commit: Option<String>,
+}
+
+impl PartialGitConfig {
+    fn merge_over(self, base: GitConfig) -> GitConfig {
+        GitConfig {
+            root_commit: self.root_commit.unwrap_or(base.root_commit),
+        }
+    }
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct PartialLintConfig {
+    max_subject_len: Option<usize>,
+    warn_subject_len: Option<usize>,
+    max_body_len: Option<usize>,
+    rules: Option<Vec<RuleName>>,
+    allow: Option<Vec<RuleName>>,
+}
+
+impl PartialLintConfig {
+    fn merge_over(self, base: LintConfig) -> LintConfig {
+        LintConfig {
+            max_subject_len: self.max_subject_len.unwrap_or(base.max_subject_len),
+            warn_subject_len: self.warn_subject_len.unwrap_or(base.warn_subject_len),
+            max_body_len: self.max_body_len.unwrap_or(base.max_body_len),
+            rules: self.rules.unwrap_or(base.rules),
+            allow: self.allow.unwrap_or(base.allow),
+        }
+    }
+}
+
+/// the repo-local `.rwth-lkp-submit.toml`, every field optional so a repo only has to
+/// specify what it wants to override
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct PartialConfig {
+    test: PartialLabTask,
+    mail: PartialMailConfig,
+    smtp: PartialSmtpConfig,
+    git: PartialGitConfig,
+    lint: PartialLintConfig,
+}
+
+impl PartialConfig {
+    fn merge_over(self, base: Config) -> Config {
+        Config {
+            test: self.test.merge_over(base.test),
+            mail: self.mail.merge_over(base.mail),
+            smtp: self.smtp.merge_over(base.smtp),
+            git: self.git.merge_over(base.git),
+            lint: self.lint.merge_over(base.lint),
+        }
+    }
+}
+
+fn write_config(config_file_path: PathBuf, config: &Config) -> anyhow::Result<()> {
+    let config_dir = config_file_path.parent().unwrap();
+    create_dir_all(config_dir).with_context(|| format!("failed to create {config_dir:?}"))?;
+    let mut output = File::create(&config_file_path)?;
+    write!(output, "{}", basic_toml::to_string(config).unwrap())
+        .with_context(|| format!("failed to write {config_file_path:?}"))
+}
+
+fn load_global_config() -> anyhow::Result<Config> {
+    let dirs = ProjectDirs::from("dev", "luckyturtle", env!("CARGO_PKG_NAME"))
+        .context("no valid home directory path could be retrieved from the operating system")?;
+    let config_file_path = dirs.config_dir().join("config.toml");
+    println!("{ACCENT}load config from {config_file_path:?}:{ACCENT:#}");
+    if !config_file_path.exists() {
+        println!("config file do not exist yet. Create default config.\nPlease configure {config_file_path:?} and retry");
+        write_config(config_file_path, &Config::default())?;
+        exit(1);
+    }
+    let config_str = read_to_string(&config_file_path)
+        .with_context(|| format!("failed to read {config_file_path:?}"))?;
+    let config = basic_toml::from_str(&config_str)
+        .with_context(|| format!("failed to deserialize config of file {config_file_path:?}"))?;
+    //just to make sure that new config options are also present at the config file
+    write_config(config_file_path, &config)?;
+    Ok(config)
+}
+
+///the worktree root of the repo `cwd` lives in, if any; `Repository::discover` itself
+///returns a repo whose `path()` points at the `.git` directory, not the worktree
+fn repo_root() -> Option<PathBuf> {
+    Repository::discover(".")
+        .ok()?
+        .workdir()
+        .map(Path::to_path_buf)
+}
+
+///search upward from the current directory for `.rwth-lkp-submit.toml`, stopping once
+///the git repo root has been checked
+fn find_repo_config() -> anyhow::Result<Option<PathBuf>> {
+    let repo_root = repo_root();
+    let mut dir = current_dir().context("failed to get current directory")?;
+    loop {
+        let candidate = dir.join(REPO_CONFIG_FILE_NAME);
+        if candidate.exists() {
+            return Ok(Some(candidate));
+        }
+        if repo_root.as_deref() == Some(dir.as_path()) {
+            return Ok(None);
+        }
+        let Some(parent) = dir.parent() else {
+            return Ok(None);
+        };
+        dir = parent.to_path_buf();
+    }
+}
+
+///load the global config and, if present, merge a per-repository config over it
+pub fn load_config() -> anyhow::Result<Config> {
+    let config = load_global_config()?;
+    let Some(repo_config_path) = find_repo_config()? else {
+        return Ok(config);
+    };
+    println!("{ACCENT}merge local config from {repo_config_path:?}:{ACCENT:#}");
+    let repo_config_str = read_to_string(&repo_config_path)
+        .with_context(|| format!("failed to read {repo_config_path:?}"))?;
+    let repo_config: PartialConfig = basic_toml::from_str(&repo_config_str)
+        .with_context(|| format!("failed to deserialize config of file {repo_config_path:?}"))?;
+    Ok(repo_config.merge_over(config))
+}
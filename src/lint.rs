@@ -0,0 +1,339 @@
+//! lint commit messages before they are turned into patches, modeled after
+//! the conventions enforced by `checkpatch.pl` / the kernel mailing list.
+use std::fmt::Display;
+
+use anyhow::Context;
+use git2::{Repository, Sort};
+use serde::{Deserialize, Serialize};
+
+use crate::ACCENT;
+
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "kebab-case")]
+pub enum RuleName {
+    SubjectLength,
+    SubjectCapitalized,
+    SubjectTrailingPeriod,
+    ForbiddenPrefix,
+    BlankLineAfterSubject,
+    BodyLineLength,
+    SignedOffBy,
+}
+
+impl Display for RuleName {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            RuleName::SubjectLength => "subject-length",
+            RuleName::SubjectCapitalized => "subject-capitalized",
+            RuleName::SubjectTrailingPeriod => "subject-trailing-period",
+            RuleName::ForbiddenPrefix => "forbidden-prefix",
+            RuleName::BlankLineAfterSubject => "blank-line-after-subject",
+            RuleName::BodyLineLength => "body-line-length",
+            RuleName::SignedOffBy => "signed-off-by",
+        };
+        write!(f, "{name}")
+    }
+}
+
+impl RuleName {
+    const ALL: [RuleName; 7] = [
+        RuleName::SubjectLength,
+        RuleName::SubjectCapitalized,
+        RuleName::SubjectTrailingPeriod,
+        RuleName::ForbiddenPrefix,
+        RuleName::BlankLineAfterSubject,
+        RuleName::BodyLineLength,
+        RuleName::SignedOffBy,
+    ];
+}
+
+#[derive(Debug, Deserialize, Clone, Serialize)]
+#[serde(default)]
+pub struct LintConfig {
+    /// subjects longer than this are a hard failure
+    pub max_subject_len: usize,
+    /// subjects longer than this (but within `max_subject_len`) only warn
+    pub warn_subject_len: usize,
+    /// body lines longer than this are a hard failure
+    pub max_body_len: usize,
+    /// rules to check
+    pub rules: Vec<RuleName>,
+    /// rules to skip even though they are in `rules`
+    pub allow: Vec<RuleName>,
+}
+
+impl Default for LintConfig {
+    fn default() -> Self {
+        Self {
+            max_subject_len: 72,
+            warn_subject_len: 50,
+            max_body_len: 75,
+            rules: RuleName::ALL.to_vec(),
+            allow: Vec::new(),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct Issue {
+    pub commit: String,
+    pub rule: RuleName,
+    pub message: String,
+    /// whether this particular violation aborts the submission, or is printed as a
+    /// warning only; the same rule can produce both (e.g. `SubjectLength` warns above
+    /// `warn_subject_len` but hard-fails above `max_subject_len`), so this travels with
+    /// the issue instead of being keyed off `rule` alone
+    pub hard: bool,
+}
+
+const FORBIDDEN_PREFIXES: [&str; 3] = ["WIP", "fixup!", "squash!"];
+
+fn check_subject(subject: &str, config: &LintConfig, issues: &mut Vec<Issue>, commit: &str) {
+    let len = subject.chars().count();
+    if len > config.max_subject_len {
+        issues.push(Issue {
+            commit: commit.to_owned(),
+            rule: RuleName::SubjectLength,
+            message: format!(
+                "subject is {len} characters long, exceeds hard limit of {}",
+                config.max_subject_len
+            ),
+            hard: true,
+        });
+    } else if len > config.warn_subject_len {
+        issues.push(Issue {
+            commit: commit.to_owned(),
+            rule: RuleName::SubjectLength,
+            message: format!(
+                "subject is {len} characters long, should be \u{2264} {}",
+                config.warn_subject_len
+            ),
+            hard: false,
+        });
+    }
+
+    let starts_capital_or_subsystem = subject
+        .split_once(':')
+        .map(|(prefix, _)| !prefix.is_empty() && !prefix.contains(' '))
+        .unwrap_or(false)
+        || subject
+            .chars()
+            .next()
+            .map(|c| c.is_uppercase())
+            .unwrap_or(false);
+    if !starts_capital_or_subsystem {
+        issues.push(Issue {
+            commit: commit.to_owned(),
+            rule: RuleName::SubjectCapitalized,
+            message: "subject should start with a capital letter or a `subsystem:` prefix"
+                .to_owned(),
+            hard: true,
+        });
+    }
+
+    if subject.trim_end().ends_with('.') {
+        issues.push(Issue {
+            commit: commit.to_owned(),
+            rule: RuleName::SubjectTrailingPeriod,
+            message: "subject should not end with a period".to_owned(),
+            hard: true,
+        });
+    }
+
+    for prefix in FORBIDDEN_PREFIXES {
+        if subject.starts_with(prefix) {
+            issues.push(Issue {
+                commit: commit.to_owned(),
+                rule: RuleName::ForbiddenPrefix,
+                message: format!("subject must not start with `{prefix}`"),
+                hard: true,
+            });
+        }
+    }
+}
+
+fn is_exempt_body_line(line: &str) -> bool {
+    let line = line.trim();
+    line.starts_with("http://")
+        || line.starts_with("https://")
+        || line.starts_with('>')
+        || line.starts_with("Signed-off-by:")
+}
+
+///check everything that follows the subject line, i.e. the raw commit message with the subject
+///(and its trailing newline) already stripped off
+fn check_body(after_subject: &str, config: &LintConfig, issues: &mut Vec<Issue>, commit: &str) {
+    if !after_subject.is_empty() && !after_subject.starts_with('\n') {
+        issues.push(Issue {
+            commit: commit.to_owned(),
+            rule: RuleName::BlankLineAfterSubject,
+            message: "missing blank line between subject and body".to_owned(),
+            hard: true,
+        });
+    }
+    let body = after_subject.trim_start_matches('\n');
+
+    let mut has_signed_off_by = false;
+    for line in body.lines() {
+        if line.starts_with("Signed-off-by:") {
+            has_signed_off_by = true;
+        }
+        let len = line.chars().count();
+        if len > config.max_body_len && !is_exempt_body_line(line) {
+            issues.push(Issue {
+                commit: commit.to_owned(),
+                rule: RuleName::BodyLineLength,
+                message: format!(
+                    "body line is {len} columns wide, exceeds {}: {line:?}",
+                    config.max_body_len
+                ),
+                hard: true,
+            });
+        }
+    }
+    if !has_signed_off_by {
+        issues.push(Issue {
+            commit: commit.to_owned(),
+            rule: RuleName::SignedOffBy,
+            message: "missing `Signed-off-by:` trailer".to_owned(),
+            hard: true,
+        });
+    }
+}
+
+/// lint every commit in `root_commit..HEAD`, returning all collected issues
+pub fn lint_commits(root_commit: &str, config: &LintConfig) -> anyhow::Result<Vec<Issue>> {
+    println!("{ACCENT}lint commit messages:{ACCENT:#}");
+    let repo = Repository::discover(".").context("failed to discover git repository")?;
+
+    let root_oid = repo
+        .revparse_single(root_commit)
+        .with_context(|| format!("failed to resolve {root_commit:?}"))?
+        .id();
+    let head_oid = repo
+        .head()
+        .context("failed to get HEAD")?
+        .peel_to_commit()
+        .context("HEAD does not point at a commit")?
+        .id();
+
+    let mut revwalk = repo.revwalk().context("failed to walk commits")?;
+    revwalk.push(head_oid)?;
+    revwalk.hide(root_oid)?;
+    revwalk.set_sorting(Sort::REVERSE | Sort::TOPOLOGICAL)?;
+
+    let active: Vec<RuleName> = config
+        .rules
+        .iter()
+        .copied()
+        .filter(|rule| !config.allow.contains(rule))
+        .collect();
+
+    let mut issues = Vec::new();
+    for oid in revwalk {
+        let oid = oid.context("failed to walk commits")?;
+        let commit = repo
+            .find_commit(oid)
+            .with_context(|| format!("failed to read commit {oid}"))?;
+        //`message()` is the raw, unwrapped commit message; unlike `summary()`/`body()` it
+        //still contains the blank line between subject and body, which is what
+        //`check_body` needs to see
+        let message = commit.message().unwrap_or_default();
+        let (subject, after_subject) = message.split_once('\n').unwrap_or((message, ""));
+        let commit = oid.to_string();
+
+        let mut commit_issues = Vec::new();
+        check_subject(subject, config, &mut commit_issues, &commit);
+        check_body(after_subject, config, &mut commit_issues, &commit);
+        issues.extend(
+            commit_issues
+                .into_iter()
+                .filter(|issue| active.contains(&issue.rule)),
+        );
+    }
+    Ok(issues)
+}
+
+/// print all issues grouped per commit and return whether a hard rule was violated
+pub fn print_issues(issues: &[Issue]) -> bool {
+    let mut hard_failure = false;
+    let mut commits: Vec<&str> = issues.iter().map(|i| i.commit.as_str()).collect();
+    commits.dedup();
+    for commit in commits {
+        println!("{ACCENT}{}:{ACCENT:#}", &commit[..12.min(commit.len())]);
+        for issue in issues.iter().filter(|i| i.commit == commit) {
+            let level = if issue.hard {
+                hard_failure = true;
+                "error"
+            } else {
+                "warning"
+            };
+            println!("  [{level}] {}: {}", issue.rule, issue.message);
+        }
+    }
+    hard_failure
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env::{current_dir, set_current_dir};
+    use std::fs::write;
+
+    use git2::{Repository, Signature};
+    use tempfile::tempdir;
+
+    use super::*;
+
+    fn commit<'a>(
+        repo: &'a Repository,
+        message: &str,
+        parent: Option<&git2::Commit<'a>>,
+    ) -> git2::Commit<'a> {
+        let sig = Signature::now("Test User", "test@example.com").unwrap();
+        let tree_id = repo.index().unwrap().write_tree().unwrap();
+        let tree = repo.find_tree(tree_id).unwrap();
+        let parents: Vec<&git2::Commit> = parent.into_iter().collect();
+        let oid = repo
+            .commit(Some("HEAD"), &sig, &sig, message, &tree, &parents)
+            .unwrap();
+        repo.find_commit(oid).unwrap()
+    }
+
+    ///a real commit with a correctly blank-separated body must not trip the
+    ///`BlankLineAfterSubject` rule, and a commit that really omits the blank line
+    ///must trip it
+    #[test]
+    fn blank_line_after_subject_rule() {
+        let dir = tempdir().unwrap();
+        let repo = Repository::init(dir.path()).unwrap();
+        let root = commit(&repo, "root", None);
+
+        write(dir.path().join("file"), "content").unwrap();
+        let mut index = repo.index().unwrap();
+        index.add_path(std::path::Path::new("file")).unwrap();
+        index.write().unwrap();
+        let well_formed = commit(
+            &repo,
+            "Add file\n\nExplain why.\n\nSigned-off-by: Test User <test@example.com>\n",
+            Some(&root),
+        );
+        commit(
+            &repo,
+            "Add file again\nExplain why, missing blank line.\n\nSigned-off-by: Test User <test@example.com>\n",
+            Some(&well_formed),
+        );
+
+        let original_dir = current_dir().unwrap();
+        set_current_dir(dir.path()).unwrap();
+        let issues = lint_commits(&root.id().to_string(), &LintConfig::default());
+        set_current_dir(original_dir).unwrap();
+        let issues = issues.unwrap();
+
+        let blank_line_issues: Vec<&Issue> = issues
+            .iter()
+            .filter(|i| i.rule == RuleName::BlankLineAfterSubject)
+            .collect();
+        assert_eq!(blank_line_issues.len(), 1);
+        assert!(blank_line_issues[0].hard);
+    }
+}
@@ -0,0 +1,248 @@
+//! submit generated patches by mail, either via the system `git send-email`
+//! or natively over SMTP.
+use std::fs::read_to_string;
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, Context};
+use lettre::message::header::{ContentType, Header, HeaderName, HeaderValue};
+use lettre::message::Message;
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::{SmtpTransport, Transport};
+use serde::{Deserialize, Serialize};
+
+use crate::{create_command, run_command, run_command_stdout, ACCENT};
+
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "kebab-case")]
+pub enum MailBackend {
+    #[default]
+    GitSendEmail,
+    Smtp,
+}
+
+#[derive(Debug, Deserialize, Clone, Serialize)]
+#[serde(default)]
+pub struct MailConfig {
+    pub to: String,
+    pub suppress_cc: bool,
+    pub backend: MailBackend,
+}
+
+impl Default for MailConfig {
+    fn default() -> Self {
+        Self {
+            to: "lkp-maintainers@os.rwth-aachen.de".to_owned(),
+            suppress_cc: true,
+            backend: MailBackend::default(),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Clone, Serialize)]
+#[serde(default)]
+pub struct SmtpConfig {
+    pub host: String,
+    pub port: u16,
+    pub username: String,
+    pub password: String,
+    pub credential_command: String,
+    pub starttls: bool,
+    pub from: String,
+}
+
+impl Default for SmtpConfig {
+    fn default() -> Self {
+        Self {
+            host: String::new(),
+            port: 587,
+            username: String::new(),
+            password: String::new(),
+            credential_command: String::new(),
+            starttls: true,
+            from: String::new(),
+        }
+    }
+}
+
+/// custom `In-Reply-To` header, lettre has no typed header for this
+#[derive(Clone)]
+struct InReplyTo(String);
+
+impl Header for InReplyTo {
+    fn name() -> HeaderName {
+        HeaderName::new_from_ascii_str("In-Reply-To")
+    }
+
+    fn parse(s: &str) -> Result<Self, Box<dyn std::error::Error + Send + Sync>> {
+        Ok(InReplyTo(s.to_owned()))
+    }
+
+    fn display(&self) -> HeaderValue {
+        HeaderValue::new(Self::name(), self.0.clone())
+    }
+}
+
+/// custom `References` header, lettre has no typed header for this
+#[derive(Clone)]
+struct References(String);
+
+impl Header for References {
+    fn name() -> HeaderName {
+        HeaderName::new_from_ascii_str("References")
+    }
+
+    fn parse(s: &str) -> Result<Self, Box<dyn std::error::Error + Send + Sync>> {
+        Ok(References(s.to_owned()))
+    }
+
+    fn display(&self) -> HeaderValue {
+        HeaderValue::new(Self::name(), self.0.clone())
+    }
+}
+
+struct ParsedPatch {
+    from: String,
+    subject: String,
+    body: String,
+}
+
+///parse the `From:`/`Subject:` headers and body out of a `.patch` file in `git format-patch`
+///layout, as written by `patch::create_patchs`
+fn parse_patch_file(path: &Path) -> anyhow::Result<ParsedPatch> {
+    let content = read_to_string(path).with_context(|| format!("failed to read patch {path:?}"))?;
+    let mut from = None;
+    let mut subject = None;
+    let mut body_lines = Vec::new();
+    let mut in_headers = true;
+    for line in content.lines().skip(1) {
+        //the first line is the mbox "From <sha1> <date>" separator, not a header
+        if in_headers {
+            if line.is_empty() {
+                in_headers = false;
+                continue;
+            }
+            if let Some(value) = line.strip_prefix("From: ") {
+                from = Some(value.to_owned());
+            } else if let Some(value) = line.strip_prefix("Subject: ") {
+                subject = Some(value.to_owned());
+            }
+            continue;
+        }
+        body_lines.push(line);
+    }
+    Ok(ParsedPatch {
+        from: from.with_context(|| format!("patch {path:?} has no From: header"))?,
+        subject: subject.with_context(|| format!("patch {path:?} has no Subject: header"))?,
+        body: body_lines.join("\n"),
+    })
+}
+
+///submit the patches as a threaded set over authenticated SMTP
+fn send_patches_smtp(
+    patch_files: &[PathBuf],
+    mail_config: &MailConfig,
+    smtp_config: &SmtpConfig,
+) -> anyhow::Result<()> {
+    println!("{ACCENT}send mails via smtp:{ACCENT:#}");
+    let password = if !smtp_config.password.is_empty() {
+        smtp_config.password.clone()
+    } else if !smtp_config.credential_command.is_empty() {
+        run_command_stdout(create_command("sh").args(["-c", &smtp_config.credential_command]))
+            .context("failed to run smtp.credential_command")?
+            .trim()
+            .to_owned()
+    } else {
+        bail!("either smtp.password or smtp.credential_command must be configured");
+    };
+
+    let mailer = if smtp_config.starttls {
+        SmtpTransport::starttls_relay(&smtp_config.host)
+    } else {
+        SmtpTransport::relay(&smtp_config.host)
+    }
+    .with_context(|| format!("failed to create smtp transport for {}", smtp_config.host))?
+    .port(smtp_config.port)
+    .credentials(Credentials::new(smtp_config.username.clone(), password))
+    .build();
+
+    let mut root_message_id: Option<String> = None;
+    for patch_file in patch_files {
+        let parsed = parse_patch_file(patch_file)?;
+        let message_id = format!(
+            "<{}@rwth-lkp-submit>",
+            patch_file
+                .file_stem()
+                .with_context(|| format!("failed to get file name of {patch_file:?}"))?
+                .to_string_lossy()
+        );
+
+        //the relay only accepts mail from the authenticated account, so `From:` must be
+        //`smtp.from`; the original commit author is preserved via `Reply-To:` instead
+        let mut builder = Message::builder()
+            .from(
+                smtp_config
+                    .from
+                    .parse()
+                    .context("failed to parse smtp.from")?,
+            )
+            .reply_to(
+                parsed
+                    .from
+                    .parse()
+                    .context("failed to parse From: header")?,
+            )
+            .to(mail_config.to.parse().context("failed to parse mail.to")?)
+            .subject(parsed.subject)
+            .message_id(Some(message_id.clone()))
+            .header(ContentType::TEXT_PLAIN);
+        if !mail_config.suppress_cc {
+            builder = builder.cc(parsed
+                .from
+                .parse()
+                .context("failed to parse From: header")?);
+        }
+        if let Some(root_id) = &root_message_id {
+            builder = builder
+                .header(InReplyTo(root_id.clone()))
+                .header(References(root_id.clone()));
+        } else {
+            root_message_id = Some(message_id);
+        }
+        let email = builder
+            .body(parsed.body)
+            .with_context(|| format!("failed to build email for {patch_file:?}"))?;
+
+        mailer
+            .send(&email)
+            .with_context(|| format!("failed to send {patch_file:?}"))?;
+    }
+    Ok(())
+}
+
+///submit the patches by shelling out to `git send-email`
+fn send_patches_git_send_email(
+    patch_files: &[PathBuf],
+    mail_config: &MailConfig,
+) -> anyhow::Result<()> {
+    println!("{ACCENT}send mails:{ACCENT:#}");
+    let mut cmd = create_command("git");
+    cmd.args(["send-email", "--to", &mail_config.to, "--confirm=never"]);
+    if mail_config.suppress_cc {
+        cmd.arg("--suppress-cc=all");
+    }
+    cmd.args(patch_files);
+    run_command(&mut cmd)?;
+    Ok(())
+}
+
+///submit `patch_files` using the backend selected in `mail_config.backend`
+pub fn send_patches(
+    patch_files: &[PathBuf],
+    mail_config: &MailConfig,
+    smtp_config: &SmtpConfig,
+) -> anyhow::Result<()> {
+    match mail_config.backend {
+        MailBackend::GitSendEmail => send_patches_git_send_email(patch_files, mail_config),
+        MailBackend::Smtp => send_patches_smtp(patch_files, mail_config, smtp_config),
+    }
+}
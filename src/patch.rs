@@ -0,0 +1,110 @@
+//! turn the commits since `root_commit` into `.patch` files, using `libgit2`
+//! instead of spawning the `git` binary.
+use std::fs::write;
+use std::path::PathBuf;
+
+use anyhow::Context;
+use git2::{EmailCreateOptions, Repository, Sort};
+use tempfile::TempDir;
+
+use crate::config::LabTask;
+use crate::ACCENT;
+
+///build a `NNNN-oid-subject.patch` file name matching the `git format-patch` convention,
+///with the commit's short oid spliced in so the file stem (and the `Message-Id:` the
+///mail backend later derives from it) stays unique across resubmissions that produce
+///an otherwise identical subject, e.g. after fixing a lint error
+fn patch_filename(index: usize, oid: git2::Oid, summary: &str) -> String {
+    let mut slug = String::new();
+    let mut last_was_dash = false;
+    for c in summary.chars() {
+        if c.is_ascii_alphanumeric() {
+            slug.push(c);
+            last_was_dash = false;
+        } else if !last_was_dash {
+            slug.push('-');
+            last_was_dash = true;
+        }
+    }
+    let slug = slug.trim_matches('-');
+    format!("{:04}-{:.12}-{slug}.patch", index + 1, oid.to_string())
+}
+
+///generate patch files and return absoulte Path to patch files
+pub fn create_patchs(
+    tmp_dir: &TempDir,
+    root_commit: &str,
+    lab_task: &LabTask,
+) -> anyhow::Result<Vec<PathBuf>> {
+    println!("{ACCENT}create patchs:{ACCENT:#}");
+    //`Repository::discover` returns a repo whose `path()` points at the `.git` directory,
+    //not the worktree; the revwalk below only needs the repo handle, not the workdir path.
+    let repo = Repository::discover(".").context("failed to discover git repository")?;
+
+    let root_oid = repo
+        .revparse_single(root_commit)
+        .with_context(|| format!("failed to resolve {root_commit:?}"))?
+        .id();
+    let head_oid = repo
+        .head()
+        .context("failed to get HEAD")?
+        .peel_to_commit()
+        .context("HEAD does not point at a commit")?
+        .id();
+
+    let mut revwalk = repo.revwalk().context("failed to walk commits")?;
+    revwalk.push(head_oid)?;
+    revwalk.hide(root_oid)?;
+    revwalk.set_sorting(Sort::REVERSE | Sort::TOPOLOGICAL)?;
+    let oids = revwalk
+        .collect::<Result<Vec<_>, _>>()
+        .context("failed to walk commits")?;
+
+    if oids.is_empty() {
+        println!("nothing to submit");
+        std::process::exit(0);
+    }
+    let count = oids.len();
+
+    let mut patch_files = Vec::with_capacity(count);
+    for (index, oid) in oids.into_iter().enumerate() {
+        let commit = repo
+            .find_commit(oid)
+            .with_context(|| format!("failed to read commit {oid}"))?;
+        let parent = commit
+            .parent(0)
+            .with_context(|| format!("commit {oid} has no parent"))?;
+        let diff = repo
+            .diff_tree_to_tree(Some(&parent.tree()?), Some(&commit.tree()?), None)
+            .with_context(|| format!("failed to diff commit {oid}"))?;
+
+        //inject the lab/task tag directly into the formatted subject of the first mail,
+        //so the CI knows the task, without a second pass over the written patch file
+        let summary = commit.summary().unwrap_or_default();
+        let summary = if index == 0 {
+            format!("{lab_task} {summary}")
+        } else {
+            summary.to_owned()
+        };
+        let body = commit.body().unwrap_or_default().to_owned();
+
+        let mut opts = EmailCreateOptions::default();
+        let email = git2::Email::from_diff(
+            &diff,
+            index + 1,
+            count,
+            &oid,
+            &summary,
+            &body,
+            &commit.author(),
+            &mut opts,
+        )
+        .with_context(|| format!("failed to format email for commit {oid}"))?;
+
+        let patch_file = tmp_dir.path().join(patch_filename(index, oid, &summary));
+        write(&patch_file, email.as_slice())
+            .with_context(|| format!("failed to write {patch_file:?}"))?;
+        patch_files.push(patch_file);
+    }
+    Ok(patch_files)
+}